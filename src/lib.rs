@@ -61,29 +61,54 @@ use std::collections::HashMap;
 use actix_session::storage::{LoadError, SaveError, SessionKey, SessionStore, UpdateError};
 use actix_web::cookie::time::Duration;
 use anyhow::{anyhow, Error};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use chacha20poly1305::{aead::Aead, AeadCore, ChaCha20Poly1305, KeyInit, Nonce};
 use chrono::{DateTime, Utc};
 use log::{debug, error};
 use serde::{Deserialize, Serialize};
 use session_key::generate_session_key;
+use std::time::Duration as StdDuration;
 use surrealdb::{
-    engine::remote::ws::Client, sql::{Id, Thing}, Surreal
+    engine::remote::ws::Client, sql::{Id, Thing}, Connection, Surreal
 };
+use tokio::task::JoinHandle;
 
 use crate::dates::add_duration_to_current;
 
 /// SurrealDB Database Connection
+///
+/// Kept as an alias for the remote WebSocket client for backward compatibility. [`SurrealSessionStore`]
+/// itself is generic over any [`surrealdb::Connection`], so embedded engines (e.g.
+/// [`surrealdb::engine::local::Mem`]) work too.
 pub type DBConnection = Surreal<Client>;
 
-#[derive(Clone)]
-pub struct SurrealSessionStore {
-    client: DBConnection,
+pub struct SurrealSessionStore<C: Connection> {
+    client: Surreal<C>,
     tb: String,
+    cipher: Option<ChaCha20Poly1305>,
 }
 
-impl SurrealSessionStore {
+// Manual impl instead of `#[derive(Clone)]`: a derive would require `C: Clone`, but `Surreal<C>`
+// is an `Arc`-backed router handle that is `Clone` unconditionally, regardless of whether the
+// underlying engine `C` is. A derived bound would make `SurrealSessionStore<C>` only `Clone` for
+// engines that happen to be `Clone` themselves, which isn't a real constraint.
+impl<C: Connection> Clone for SurrealSessionStore<C> {
+    fn clone(&self) -> Self {
+        Self {
+            client: self.client.clone(),
+            tb: self.tb.clone(),
+            cipher: self.cipher.clone(),
+        }
+    }
+}
+
+impl<C: Connection> SurrealSessionStore<C> {
     /// Creates a SurrealSessionStore from an existing and logged in connection
     ///
-    /// Takes the [DBConnection] and the database table to be used as args
+    /// Takes a [`Surreal<C>`] and the database table to be used as args. Any engine implementing
+    /// [`surrealdb::Connection`] works here, including the embedded `engine::local` engines (Mem,
+    /// RocksDb, SurrealKv), not just the remote `engine::remote::ws` client aliased by
+    /// [`DBConnection`].
     ///
     /// This function does NOT check for signin status, namespace or database. It also doesn't
     /// error if one of these are set up wrong.
@@ -106,11 +131,262 @@ impl SurrealSessionStore {
     ///     let session_store = SurrealSessionStore::from_connection(db, "sessions");
     /// }
     /// ```
-    pub fn from_connection(db: DBConnection, tb: &str) -> SurrealSessionStore {
+    pub fn from_connection(db: Surreal<C>, tb: &str) -> SurrealSessionStore<C> {
         SurrealSessionStore {
             client: db,
             tb: tb.to_owned(),
+            cipher: None,
+        }
+    }
+
+    /// Enables encryption-at-rest for the serialized session payload
+    ///
+    /// Without this, the `token` field is stored as plaintext `serde_json`, so anyone with read
+    /// access to the database sees full session contents. This is a concern separate from the
+    /// signed session cookie, which never contains the session state itself.
+    ///
+    /// When set, the JSON body is AEAD-encrypted with ChaCha20-Poly1305 before `create`/`update`
+    /// and transparently decrypted in `load`, using a random per-record nonce stored alongside
+    /// the ciphertext. When no key is configured (the default), behavior is unchanged.
+    ///
+    /// ## Example
+    ///
+    /// ```no_run
+    /// use std::io;
+    /// use actix_session_surrealdb::SurrealSessionStore;
+    /// use surrealdb::{engine::remote::ws::Ws, Surreal};
+    ///
+    /// #[actix_web::main]
+    /// async fn main() -> io::Result<()> {
+    ///     let db = Surreal::new::<Ws>("127.0.0.1:8000").await.unwrap();
+    ///     // .. signin, use_ns, use_db ..
+    ///
+    ///     let key = [0u8; 32]; // load this from a secret store in practice
+    ///     let session_store = SurrealSessionStore::from_connection(db, "sessions").with_encryption(key);
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    #[must_use]
+    pub fn with_encryption(mut self, key: [u8; 32]) -> SurrealSessionStore<C> {
+        self.cipher = Some(ChaCha20Poly1305::new_from_slice(&key).expect("key is exactly 32 bytes"));
+        self
+    }
+
+    /// Encrypts `plaintext` with the configured cipher, or returns it unchanged if encryption isn't enabled
+    fn encode_token(&self, plaintext: &str) -> Result<String, Error> {
+        let Some(cipher) = &self.cipher else {
+            return Ok(plaintext.to_owned());
+        };
+
+        let nonce = ChaCha20Poly1305::generate_nonce(&mut rand::rngs::OsRng);
+        let ciphertext = cipher
+            .encrypt(&nonce, plaintext.as_bytes())
+            .map_err(|_| anyhow!("Failed to encrypt session payload"))?;
+
+        let mut combined = nonce.to_vec();
+        combined.extend_from_slice(&ciphertext);
+
+        Ok(STANDARD.encode(combined))
+    }
+
+    /// Decrypts `stored` with the configured cipher, or returns it unchanged if encryption isn't enabled
+    fn decode_token(&self, stored: &str) -> Result<String, Error> {
+        let Some(cipher) = &self.cipher else {
+            return Ok(stored.to_owned());
+        };
+
+        let combined = STANDARD.decode(stored).map_err(|e| anyhow!("Failed to decode encrypted session payload: {e}"))?;
+        if combined.len() < 12 {
+            return Err(anyhow!("Encrypted session payload is too short"));
+        }
+        let (nonce_bytes, ciphertext) = combined.split_at(12);
+        let nonce = Nonce::from_slice(nonce_bytes);
+
+        let plaintext = cipher.decrypt(nonce, ciphertext).map_err(|_| anyhow!("Failed to decrypt session payload"))?;
+
+        String::from_utf8(plaintext).map_err(|e| anyhow!("Decrypted session payload was not valid UTF-8: {e}"))
+    }
+
+    /// Bootstraps the schema for the session table
+    ///
+    /// Defines `self.tb` as a schemafull table with `token` and `expiry` fields, plus an index on
+    /// `expiry` (the bulk-cleanup query in [`Self::delete_expired`] filters on it, and without the index
+    /// large session tables get a full scan). Every `DEFINE` is issued with `IF NOT EXISTS`, so
+    /// this is idempotent and safe to call on every startup.
+    ///
+    /// ## Example
+    ///
+    /// Calling `migrate` twice is safe:
+    ///
+    /// ```
+    /// # use surrealdb::{engine::local::Mem, Surreal};
+    /// # use actix_session_surrealdb::SurrealSessionStore;
+    /// # #[tokio::main]
+    /// # async fn main() -> surrealdb::Result<()> {
+    /// let db = Surreal::new::<Mem>(()).await?;
+    /// db.use_ns("test").use_db("test").await?;
+    ///
+    /// let session_store = SurrealSessionStore::from_connection(db, "sessions");
+    /// session_store.migrate().await.expect("first migration to succeed");
+    /// session_store.migrate().await.expect("second migration to also succeed");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn migrate(&self) -> Result<(), Error> {
+        debug!("Running session table migration..");
+        let query = format!(
+            "DEFINE TABLE IF NOT EXISTS {tb} SCHEMAFULL;
+             DEFINE FIELD IF NOT EXISTS token ON TABLE {tb} TYPE string;
+             DEFINE FIELD IF NOT EXISTS expiry ON TABLE {tb} TYPE datetime;
+             DEFINE INDEX IF NOT EXISTS expiry ON TABLE {tb} COLUMNS expiry;",
+            tb = self.tb
+        );
+
+        self.client.query(query).await.map_err(|e| anyhow!("Failed to migrate session table: {e}"))?.check().map_err(|e| anyhow!("Failed to migrate session table: {e}"))?;
+
+        Ok(())
+    }
+
+    /// Deletes every session record in `self.tb` whose `expiry` has already passed
+    ///
+    /// Issues a single `DELETE ... WHERE expiry < time::now()` query. Sessions are normally only
+    /// removed as a side effect of [`SessionStore::load`] being called for that exact key, so a
+    /// session that's created and never touched again would otherwise linger forever. Call this
+    /// periodically (see [`Self::spawn_reaper`]) or from your own scheduler to keep the table
+    /// bounded.
+    pub async fn delete_expired(&self) -> Result<(), Error> {
+        debug!("Deleting expired sessions..");
+        self.client
+            .query("DELETE type::table($tb) WHERE expiry < time::now()")
+            .bind(("tb", self.tb.clone()))
+            .await
+            .map_err(|e| anyhow!("Failed to delete expired sessions: {e}"))?
+            .check()
+            .map_err(|e| anyhow!("Failed to delete expired sessions: {e}"))?;
+
+        Ok(())
+    }
+
+    /// Spawns a background task that calls [`Self::delete_expired`] on a fixed interval
+    ///
+    /// This is the easiest way to bound the size of the session table without relying on read
+    /// traffic to evict expired records. The returned [`JoinHandle`] keeps running until it is
+    /// aborted or the process exits; dropping it does not stop the task.
+    ///
+    /// Callers that already run their own scheduler (e.g. a cron-like job runner) can skip this
+    /// and call [`Self::delete_expired`] from there instead.
+    ///
+    /// ## Example
+    ///
+    /// ```no_run
+    /// use std::io;
+    /// use actix_session_surrealdb::SurrealSessionStore;
+    /// use surrealdb::{engine::remote::ws::Ws, Surreal};
+    ///
+    /// #[actix_web::main]
+    /// async fn main() -> io::Result<()> {
+    ///     let db = Surreal::new::<Ws>("127.0.0.1:8000").await.unwrap();
+    ///     // .. signin, use_ns, use_db ..
+    ///
+    ///     let session_store = SurrealSessionStore::from_connection(db, "sessions");
+    ///     session_store.clone().spawn_reaper(std::time::Duration::from_secs(60 * 60));
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn spawn_reaper(self, interval: StdDuration) -> JoinHandle<()>
+    where
+        C: 'static,
+    {
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                ticker.tick().await;
+                if let Err(e) = self.delete_expired().await {
+                    error!("Session reaper failed to delete expired sessions: {e}");
+                }
+            }
+        })
+    }
+
+    /// Deletes every session record in `self.tb`, regardless of expiry
+    ///
+    /// Useful for administrative actions like forcing a logout of every user, e.g. after
+    /// rotating the signing secret and wanting to invalidate all previously issued cookies.
+    ///
+    /// ## Example
+    ///
+    /// ```no_run
+    /// use std::io;
+    /// use actix_session_surrealdb::SurrealSessionStore;
+    /// use surrealdb::{engine::remote::ws::Ws, Surreal};
+    ///
+    /// #[actix_web::main]
+    /// async fn main() -> io::Result<()> {
+    ///     let db = Surreal::new::<Ws>("127.0.0.1:8000").await.unwrap();
+    ///     // .. signin, use_ns, use_db ..
+    ///
+    ///     let session_store = SurrealSessionStore::from_connection(db, "sessions");
+    ///     session_store.clear().await.expect("clearing all sessions to succeed");
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn clear(&self) -> Result<(), Error> {
+        debug!("Clearing all sessions..");
+        self.client
+            .query("DELETE type::table($tb)")
+            .bind(("tb", self.tb.clone()))
+            .await
+            .map_err(|e| anyhow!("Failed to clear session table: {e}"))?
+            .check()
+            .map_err(|e| anyhow!("Failed to clear session table: {e}"))?;
+
+        Ok(())
+    }
+
+    /// Returns the number of session records currently stored in `self.tb`
+    ///
+    /// Useful for monitoring table growth. Note that this includes expired-but-not-yet-reaped
+    /// records; call [`Self::delete_expired`] first if an exact live count is needed.
+    ///
+    /// ## Example
+    ///
+    /// ```no_run
+    /// use std::io;
+    /// use actix_session_surrealdb::SurrealSessionStore;
+    /// use surrealdb::{engine::remote::ws::Ws, Surreal};
+    ///
+    /// #[actix_web::main]
+    /// async fn main() -> io::Result<()> {
+    ///     let db = Surreal::new::<Ws>("127.0.0.1:8000").await.unwrap();
+    ///     // .. signin, use_ns, use_db ..
+    ///
+    ///     let session_store = SurrealSessionStore::from_connection(db, "sessions");
+    ///     let sessions = session_store.count().await.expect("count to succeed");
+    ///     println!("{sessions} sessions stored");
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn count(&self) -> Result<usize, Error> {
+        debug!("Counting sessions..");
+        #[derive(Debug, Deserialize)]
+        struct CountRecord {
+            count: usize,
         }
+
+        let count: Option<CountRecord> = self
+            .client
+            .query("SELECT count() FROM type::table($tb) GROUP ALL")
+            .bind(("tb", self.tb.clone()))
+            .await
+            .map_err(|e| anyhow!("Failed to count session table: {e}"))?
+            .take(0)
+            .map_err(|e| anyhow!("Failed to count session table: {e}"))?;
+
+        Ok(count.map(|c| c.count).unwrap_or(0))
     }
 }
 
@@ -131,7 +407,7 @@ pub(crate) struct KeyRecordPatch {
 }
 
 #[async_trait::async_trait(?Send)]
-impl SessionStore for SurrealSessionStore {
+impl<C: Connection> SessionStore for SurrealSessionStore<C> {
     async fn load(&self, session_key: &SessionKey) -> Result<Option<SessionState>, LoadError> {
         debug!("Loading sessionstate from db..");
         let thingy = Thing {
@@ -159,11 +435,14 @@ impl SessionStore for SurrealSessionStore {
             return Ok(None);
         }
 
-        Ok(serde_json::from_str(&record.token).map_err(Into::into).map_err(LoadError::Deserialization)?)
+        let token = self.decode_token(&record.token).map_err(LoadError::Other)?;
+
+        Ok(serde_json::from_str(&token).map_err(Into::into).map_err(LoadError::Deserialization)?)
     }
 
     async fn save(&self, session_state: SessionState, ttl: &Duration) -> Result<SessionKey, SaveError> {
         let body = serde_json::to_string(&session_state).map_err(Into::into).map_err(SaveError::Serialization)?;
+        let body = self.encode_token(&body).map_err(SaveError::Other)?;
         let session_key = generate_session_key();
         let id = session_key.as_ref().to_owned();
 
@@ -198,6 +477,7 @@ impl SessionStore for SurrealSessionStore {
         &self, session_key: SessionKey, session_state: SessionState, ttl: &Duration,
     ) -> Result<SessionKey, UpdateError> {
         let body = serde_json::to_string(&session_state).map_err(Into::into).map_err(UpdateError::Serialization)?;
+        let body = self.encode_token(&body).map_err(UpdateError::Other)?;
 
         let id = session_key.as_ref().to_owned();
         let thingy = Thing {
@@ -271,3 +551,67 @@ impl SessionStore for SurrealSessionStore {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use surrealdb::engine::local::Mem;
+
+    async fn store_with_key(key: [u8; 32]) -> SurrealSessionStore<Mem> {
+        let db = Surreal::new::<Mem>(()).await.expect("in-memory engine to start");
+        SurrealSessionStore::from_connection(db, "sessions").with_encryption(key)
+    }
+
+    #[tokio::test]
+    async fn encode_decode_round_trip() {
+        let store = store_with_key([1u8; 32]).await;
+        let plaintext = r#"{"user_id":"42"}"#;
+
+        let encoded = store.encode_token(plaintext).expect("encoding to succeed");
+        assert_ne!(encoded, plaintext);
+
+        let decoded = store.decode_token(&encoded).expect("decoding to succeed");
+        assert_eq!(decoded, plaintext);
+    }
+
+    #[tokio::test]
+    async fn decode_fails_with_wrong_key() {
+        let encoding_store = store_with_key([1u8; 32]).await;
+        let decoding_store = store_with_key([2u8; 32]).await;
+
+        let encoded = encoding_store.encode_token("secret session state").expect("encoding to succeed");
+
+        assert!(decoding_store.decode_token(&encoded).is_err());
+    }
+
+    #[tokio::test]
+    async fn decode_fails_with_tampered_ciphertext() {
+        let store = store_with_key([1u8; 32]).await;
+
+        let encoded = store.encode_token("secret session state").expect("encoding to succeed");
+        let mut combined = STANDARD.decode(&encoded).expect("encoded token to be valid base64");
+        let last = combined.len() - 1;
+        combined[last] ^= 0x01;
+        let tampered = STANDARD.encode(combined);
+
+        assert!(store.decode_token(&tampered).is_err());
+    }
+
+    #[tokio::test]
+    async fn decode_fails_on_too_short_payload() {
+        let store = store_with_key([1u8; 32]).await;
+
+        let too_short = STANDARD.encode(b"short");
+        assert!(store.decode_token(&too_short).is_err());
+    }
+
+    #[tokio::test]
+    async fn without_a_key_tokens_pass_through_unchanged() {
+        let db = Surreal::new::<Mem>(()).await.expect("in-memory engine to start");
+        let store = SurrealSessionStore::from_connection(db, "sessions");
+
+        let plaintext = r#"{"user_id":"42"}"#;
+        assert_eq!(store.encode_token(plaintext).unwrap(), plaintext);
+        assert_eq!(store.decode_token(plaintext).unwrap(), plaintext);
+    }
+}